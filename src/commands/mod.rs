@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use structopt::StructOpt;
+
+use crate::Result;
+
+pub mod run;
+pub mod setup;
+pub mod upgrade;
+
+/// Top-level subcommands, dispatched from `main`.
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Run a command under the resolved Python interpreter.
+    Run(run::Opt),
+    /// Upgrade an installed toolchain to the latest release matching its requirement.
+    Upgrade(upgrade::Opt),
+}
+
+pub fn dispatch(command: Command, toolchains_dir: &Path) -> Result<()> {
+    match command {
+        Command::Run(opt) => run::run(&opt, toolchains_dir),
+        Command::Upgrade(opt) => upgrade::run(&opt, toolchains_dir),
+    }
+}
@@ -0,0 +1,35 @@
+use std::{path::Path, process::Command};
+
+use structopt::StructOpt;
+
+use crate::{
+    selected::VersionOrPath,
+    toolchain::installed::{resolve_toolchain, resolve_toolchain_for_script},
+    Result,
+};
+
+#[derive(Debug, StructOpt)]
+pub struct Opt {
+    /// Explicit version or path to use, bypassing the rest of the resolution pipeline.
+    #[structopt(long = "version")]
+    pub version: Option<VersionOrPath>,
+
+    /// Command (and arguments) to run under the resolved Python interpreter.
+    #[structopt(required = true)]
+    pub command: Vec<String>,
+}
+
+pub fn run(opt: &Opt, toolchains_dir: &Path) -> Result<()> {
+    // Mirrors `shim.rs`: when `command[0]` names a script file, let its shebang
+    // inform the resolution pipeline the same way a shim's script argument does.
+    let toolchain = match opt.command.first() {
+        Some(script) if Path::new(script).is_file() => {
+            resolve_toolchain_for_script(script, opt.version.clone(), toolchains_dir)?
+        }
+        _ => resolve_toolchain(opt.version.as_ref(), toolchains_dir)?,
+    };
+    log::debug!("`run` resolved to {:?}", toolchain);
+
+    let status = Command::new(&toolchain.location).args(&opt.command).status()?;
+    std::process::exit(status.code().unwrap_or(1));
+}
@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use semver::VersionReq;
+use structopt::StructOpt;
+
+use crate::{toolchain::installed::InstalledToolchain, Result};
+
+#[derive(Debug, StructOpt)]
+pub struct Opt {
+    /// Requirement identifying the installed toolchain to upgrade, e.g. `~3.11`.
+    pub version_req: VersionReq,
+
+    /// Don't write an install manifest after upgrading, matching cargo's
+    /// unstable `install --no-track`. Toolchains installed this way (or
+    /// before manifests existed) can still be upgraded, but their extra
+    /// packages won't be rediscovered and re-applied automatically.
+    #[structopt(long)]
+    pub no_track: bool,
+}
+
+pub fn run(opt: &Opt, toolchains_dir: &Path) -> Result<()> {
+    let installed = InstalledToolchain::from_path_matching(toolchains_dir, &opt.version_req)
+        .ok_or_else(|| {
+            crate::toolchain::installed::ToolchainNotInstalled::new(opt.version_req.clone())
+        })?;
+    log::debug!("Found installed toolchain {:?} matching {}", installed, opt.version_req);
+
+    let manifest = installed.load_manifest()?;
+    let extra_packages = manifest
+        .as_ref()
+        .map(|m| m.extra_packages.clone())
+        .unwrap_or_else(|| {
+            log::warn!(
+                "No install manifest found for {}; upgrading without re-tracking its extra packages",
+                installed.version
+            );
+            Vec::new()
+        });
+
+    let latest = crate::toolchain::available::find_latest_matching(&opt.version_req)?;
+    if latest <= installed.version {
+        log::info!(
+            "{} is already the latest version satisfying {}",
+            installed.version,
+            opt.version_req
+        );
+        return Ok(());
+    }
+
+    log::info!("Upgrading {} -> {} (matching {})", installed.version, latest, opt.version_req);
+    let source = crate::toolchain::install::install_in_place(&installed.location, &latest)?;
+
+    if !extra_packages.is_empty() {
+        log::debug!("Re-applying extra packages: {:?}", extra_packages);
+        crate::toolchain::install::install_extra_packages(&installed.location, &extra_packages)?;
+    }
+
+    let upgraded = InstalledToolchain {
+        location: installed.location,
+        version: latest,
+    };
+    upgraded.save_manifest(&opt.version_req, &source, &extra_packages, opt.no_track)?;
+
+    Ok(())
+}
@@ -21,8 +21,33 @@ pub fn home_env_variable() -> &'static str {
     &HOME_ENV_VARIABLE
 }
 
+/// Return the environment variable used to request a default Python version
+/// without a `.python-version` file, e.g. `HYGEIA_PYTHON`.
+pub fn python_env_variable() -> &'static str {
+    lazy_static! {
+        static ref PYTHON_ENV_VARIABLE: String =
+            format!("{}_PYTHON", executable_name_from_env!().to_uppercase());
+    }
+    &PYTHON_ENV_VARIABLE
+}
+
+/// Same as [`python_env_variable`], but mirroring the `py` launcher's
+/// `PY_PYTHON3`, e.g. `HYGEIA_PYTHON3`.
+pub fn python3_env_variable() -> &'static str {
+    lazy_static! {
+        static ref PYTHON3_ENV_VARIABLE: String =
+            format!("{}_PYTHON3", executable_name_from_env!().to_uppercase());
+    }
+    &PYTHON3_ENV_VARIABLE
+}
+
 pub const INFO_FILE: &str = concat!("installed_by_", executable_name_from_env!(), ".txt");
 
+/// Structured, per-toolchain record of how and why a toolchain was installed.
+/// Lives alongside [`INFO_FILE`] (which is kept around as the legacy,
+/// `--no-track`-compatible marker) in the toolchain's install directory.
+pub const INSTALL_MANIFEST_FILE: &str = concat!(executable_name_from_env!(), "-install.toml");
+
 pub const EXTRA_PACKAGES_FILENAME: &str = "extra-packages-to-install.txt";
 
 pub const EXTRA_PACKAGES_FILENAME_CONTENT: &str = include_str!("../extra-packages-to-install.txt");
@@ -11,7 +11,7 @@ use semver::VersionReq;
 
 use crate::{constants::TOOLCHAIN_FILE, utils, Result};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VersionOrPath {
     VersionReq(semver::VersionReq),
     Path(PathBuf),
@@ -21,12 +21,10 @@ impl FromStr for VersionOrPath {
     type Err = std::io::Error;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        // One can use 'latest' to mean '*'
+        // One can use 'latest' to mean 'any version', i.e. 'VersionReq::any()'.
         if s == "latest" {
-            "*"
-        } else {
-            s
-        };
+            return Ok(VersionOrPath::VersionReq(VersionReq::any()));
+        }
 
         match semver::VersionReq::parse(s) {
             Ok(version_req) => {
@@ -52,9 +50,23 @@ impl FromStr for VersionOrPath {
     }
 }
 
+/// Parse a `VersionReq`, accepting the same `latest` -> `VersionReq::any()`
+/// alias as `VersionOrPath` and `.python-version` lines. Shared so every
+/// source of a version requirement (the file, `HYGEIA_PYTHON`, ...) agrees on it.
+pub fn parse_version_req(s: &str) -> Result<VersionReq> {
+    if s == "latest" {
+        return Ok(VersionReq::any());
+    }
+    Ok(s.parse()?)
+}
+
 #[derive(Debug, Clone)]
 pub struct SelectedVersion {
-    pub version: VersionReq,
+    /// Requirements found in the file, one per non-empty/non-comment line, in
+    /// priority order (pyenv allows listing several versions, trying each in
+    /// turn until one is installed). A classic single-version file simply
+    /// yields a single-element list.
+    pub versions: Vec<VersionReq>,
 }
 
 pub fn load_selected_toolchain_file() -> Option<Result<SelectedVersion>> {
@@ -88,17 +100,26 @@ impl SelectedVersion {
         log::debug!("Reading configuration from file {:?}", path.as_ref());
 
         let input = File::open(path)?;
-        let buffered = BufReader::new(input);
+        SelectedVersion::from_reader(BufReader::new(input))
+    }
 
-        // Read first line only
-        let line = match buffered.lines().next() {
-            None => return Err(format_err!("File does not even contains a line")),
-            Some(line_result) => line_result?,
-        };
-        let version: VersionReq = line.parse()?;
-        log::debug!("Found version \"{}\"", version);
+    fn from_reader<R: BufRead>(reader: R) -> Result<SelectedVersion> {
+        let mut versions = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            versions.push(parse_version_req(line)?);
+        }
 
-        Ok(SelectedVersion { version })
+        if versions.is_empty() {
+            return Err(format_err!("File does not contain any version requirement"));
+        }
+        log::debug!("Found versions {:?}", versions);
+
+        Ok(SelectedVersion { versions })
     }
 
     pub fn save(&self) -> Result<usize> {
@@ -108,16 +129,20 @@ impl SelectedVersion {
     pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<usize> {
         log::debug!("Writing configuration to file {:?}", path.as_ref());
 
-        let version = format!("{}", self.version);
         let mut output = File::create(&path)?;
-        let l1 = output.write(version.as_bytes())?;
-        let l2 = output.write(b"\n")?;
-        Ok(l1 + l2)
+        let mut written = 0;
+        for version in &self.versions {
+            written += output.write(format!("{}", version).as_bytes())?;
+            written += output.write(b"\n")?;
+        }
+        Ok(written)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::Cursor;
+
     use super::*;
 
     #[test]
@@ -158,4 +183,49 @@ mod tests {
             VersionOrPath::VersionReq(VersionReq::parse(v).unwrap())
         );
     }
+
+    #[test]
+    fn version_or_path_from_str_success_latest_alias() {
+        let vop: VersionOrPath = "latest".parse().unwrap();
+        assert_eq!(vop, VersionOrPath::VersionReq(VersionReq::any()));
+    }
+
+    #[test]
+    fn selected_version_from_reader_single_line_backward_compatible() {
+        let selected = SelectedVersion::from_reader(Cursor::new("3.7.4\n")).unwrap();
+        assert_eq!(selected.versions, vec![VersionReq::parse("3.7.4").unwrap()]);
+    }
+
+    #[test]
+    fn selected_version_from_reader_pyenv_multi_line_ordered_fallbacks() {
+        let selected =
+            SelectedVersion::from_reader(Cursor::new("3.7.4\n~3.6\nlatest\n")).unwrap();
+        assert_eq!(
+            selected.versions,
+            vec![
+                VersionReq::parse("3.7.4").unwrap(),
+                VersionReq::parse("~3.6").unwrap(),
+                VersionReq::any(),
+            ]
+        );
+    }
+
+    #[test]
+    fn selected_version_from_reader_skips_blank_and_comment_lines() {
+        let selected =
+            SelectedVersion::from_reader(Cursor::new("# preferred\n3.7.4\n\n# fallback\n~3.6\n"))
+                .unwrap();
+        assert_eq!(
+            selected.versions,
+            vec![
+                VersionReq::parse("3.7.4").unwrap(),
+                VersionReq::parse("~3.6").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn selected_version_from_reader_empty_file_fails() {
+        assert!(SelectedVersion::from_reader(Cursor::new("")).is_err());
+    }
 }
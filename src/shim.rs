@@ -0,0 +1,20 @@
+use std::{path::Path, process::Command};
+
+use crate::{toolchain::installed::resolve_toolchain_for_script, Result};
+
+/// Entry point for the shims installed under `shims/`: resolve which
+/// toolchain to use for `args` (using the script's own shebang when `args[0]`
+/// names an existing file) and `exec` into it.
+pub fn run_shim(toolchains_dir: &Path, shim_name: &str, args: &[String]) -> Result<()> {
+    let toolchain = match args.first() {
+        Some(script) if Path::new(script).is_file() => {
+            resolve_toolchain_for_script(script, None, toolchains_dir)?
+        }
+        _ => crate::toolchain::installed::resolve_toolchain(None, toolchains_dir)?,
+    };
+
+    log::debug!("Shim {:?} resolved to {:?}", shim_name, toolchain);
+
+    let status = Command::new(&toolchain.location).args(args).status()?;
+    std::process::exit(status.code().unwrap_or(1));
+}
@@ -0,0 +1,29 @@
+use failure::format_err;
+use regex::Regex;
+use semver::{Version, VersionReq};
+
+use crate::Result;
+
+/// Index `upgrade` queries to discover newer releases; the same source
+/// toolchains are built from.
+pub const PYTHON_FTP_INDEX: &str = "https://www.python.org/ftp/python/";
+
+/// Query the upstream Python FTP index for the highest release version
+/// satisfying `version_req`.
+pub fn find_latest_matching(version_req: &VersionReq) -> Result<Version> {
+    let body = reqwest::blocking::get(PYTHON_FTP_INDEX)?.text()?;
+
+    let dir_re = Regex::new(r#"href="(\d+\.\d+\.\d+)/""#)?;
+    dir_re
+        .captures_iter(&body)
+        .filter_map(|caps| Version::parse(&caps[1]).ok())
+        .filter(|version| version_req.matches(version))
+        .max()
+        .ok_or_else(|| {
+            format_err!(
+                "No release found on {} matching {}",
+                PYTHON_FTP_INDEX,
+                version_req
+            )
+        })
+}
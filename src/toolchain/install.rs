@@ -0,0 +1,74 @@
+use std::{fs, path::Path, process::Command};
+
+use failure::format_err;
+use semver::Version;
+
+use crate::{toolchain::available::PYTHON_FTP_INDEX, Result};
+
+/// Download and build `version` from source, replacing whatever is currently
+/// installed at `location`'s prefix directory. `location` is the toolchain's
+/// `python` executable itself (e.g. `<prefix>/bin/python3.10`), same as what
+/// the shim and `run` command execute directly.
+pub fn install_in_place(location: &Path, version: &Version) -> Result<String> {
+    let bin_dir = location
+        .parent()
+        .ok_or_else(|| format_err!("Cannot get parent directory of {:?}", location))?;
+    let install_dir = bin_dir
+        .parent()
+        .ok_or_else(|| format_err!("Cannot get prefix directory of {:?}", bin_dir))?;
+
+    let source_url = format!("{}{}/Python-{}.tar.xz", PYTHON_FTP_INDEX, version, version);
+    log::info!("Downloading {} to rebuild {:?}", source_url, install_dir);
+
+    let tarball = reqwest::blocking::get(&source_url)?.bytes()?;
+    let build_dir = install_dir.with_extension("upgrade-build");
+    fs::create_dir_all(&build_dir)?;
+    tar::Archive::new(xz2::read::XzDecoder::new(&tarball[..])).unpack(&build_dir)?;
+
+    let src_dir = build_dir.join(format!("Python-{}", version));
+    let status = Command::new("./configure")
+        .arg(format!("--prefix={}", install_dir.display()))
+        .current_dir(&src_dir)
+        .status()?;
+    if !status.success() {
+        return Err(format_err!("configure failed for Python {}", version));
+    }
+
+    let status = Command::new("make")
+        .arg("install")
+        .current_dir(&src_dir)
+        .status()?;
+    if !status.success() {
+        return Err(format_err!("make install failed for Python {}", version));
+    }
+
+    fs::remove_dir_all(&build_dir)?;
+
+    Ok(source_url)
+}
+
+/// Re-install `extra_packages` (as previously recorded in the install
+/// manifest) into the toolchain at `location` via its own `pip3`, which lives
+/// right next to `location` in the same `bin` directory.
+pub fn install_extra_packages(location: &Path, extra_packages: &[String]) -> Result<()> {
+    if extra_packages.is_empty() {
+        return Ok(());
+    }
+
+    let pip = location
+        .parent()
+        .ok_or_else(|| format_err!("Cannot get parent directory of {:?}", location))?
+        .join("pip3");
+
+    log::info!("Re-installing extra packages: {:?}", extra_packages);
+    let status = Command::new(pip).arg("install").args(extra_packages).status()?;
+
+    if !status.success() {
+        return Err(format_err!(
+            "Failed to install extra packages: {:?}",
+            extra_packages
+        ));
+    }
+
+    Ok(())
+}
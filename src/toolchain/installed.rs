@@ -1,12 +1,21 @@
 use std::{
+    env, fs,
     fs::File,
-    io::Write,
+    io::{BufRead, BufReader, Write},
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+use failure::format_err;
 use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
 
-use crate::{constants::TOOLCHAIN_FILE, toolchain::get_python_versions_from_path, Result};
+use crate::{
+    constants::{self, TOOLCHAIN_FILE},
+    selected::{self, VersionOrPath},
+    toolchain::get_python_versions_from_path,
+    Result,
+};
 
 #[derive(Debug, Clone, failure::Fail)]
 #[fail(display = "Python version {} not found!", version)]
@@ -14,6 +23,16 @@ pub struct ToolchainNotInstalled {
     version: VersionReq,
 }
 
+impl ToolchainNotInstalled {
+    pub fn new(version: VersionReq) -> ToolchainNotInstalled {
+        ToolchainNotInstalled { version }
+    }
+
+    pub fn any() -> ToolchainNotInstalled {
+        ToolchainNotInstalled::new(VersionReq::any())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct InstalledToolchain {
     pub location: PathBuf,
@@ -26,6 +45,24 @@ pub struct NotInstalledToolchain {
     pub location: Option<PathBuf>,
 }
 
+/// Structured record of how a toolchain was installed, persisted next to it
+/// as [`constants::INSTALL_MANIFEST_FILE`]. Supplements the opaque [`crate::INFO_FILE`]
+/// marker (still honoured as the legacy marker) with enough detail to drive `upgrade`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InstallManifest {
+    /// Exact version that was installed.
+    pub version: Version,
+    /// Requirement the user asked for, e.g. `~3.11`; used by `upgrade` to find
+    /// a newer patch release satisfying the same requirement.
+    pub requested: VersionReq,
+    /// Unix timestamp (seconds) of when the install completed.
+    pub installed_at: u64,
+    /// Where the toolchain came from, e.g. the download URL or `"built from source"`.
+    pub source: String,
+    /// Extra packages installed from [`constants::EXTRA_PACKAGES_FILENAME`], re-applied on upgrade.
+    pub extra_packages: Vec<String>,
+}
+
 impl InstalledToolchain {
     pub fn from_path<P>(path: P) -> Option<InstalledToolchain>
     where
@@ -43,16 +80,88 @@ impl InstalledToolchain {
         })
     }
 
+    /// Like [`InstalledToolchain::from_path`], but only considers versions
+    /// matching `version_req`, returning the highest one that does.
+    pub fn from_path_matching<P>(path: P, version_req: &VersionReq) -> Option<InstalledToolchain>
+    where
+        P: AsRef<Path>,
+    {
+        let versions_found = get_python_versions_from_path(path.as_ref());
+        log::debug!("versions_found: {:?}", versions_found);
+
+        versions_found
+            .into_iter()
+            .filter(|(version, _)| version_req.matches(version))
+            .max_by(|x, y| x.0.cmp(&y.0))
+            .map(|(version, location)| InstalledToolchain { version, location })
+    }
+
     pub fn is_custom_install(&self) -> bool {
         match self.location.parent() {
             None => {
                 log::error!("Cannot get parent directory of {:?}", self.location);
                 false
             }
-            Some(parent) => parent.join(crate::INFO_FILE).exists(),
+            Some(parent) => {
+                parent.join(crate::INFO_FILE).exists()
+                    || parent.join(constants::INSTALL_MANIFEST_FILE).exists()
+            }
         }
     }
 
+    fn install_dir(&self) -> Result<&Path> {
+        self.location
+            .parent()
+            .ok_or_else(|| format_err!("Cannot get parent directory of {:?}", self.location))
+    }
+
+    /// Write the structured install manifest next to this toolchain, unless
+    /// `no_track` is set (matching cargo's unstable `install --no-track`), in
+    /// which case only the legacy [`crate::INFO_FILE`] marker is relied upon.
+    pub fn save_manifest(
+        &self,
+        requested: &VersionReq,
+        source: &str,
+        extra_packages: &[String],
+        no_track: bool,
+    ) -> Result<()> {
+        if no_track {
+            log::debug!(
+                "--no-track set; not writing {}",
+                constants::INSTALL_MANIFEST_FILE
+            );
+            return Ok(());
+        }
+
+        let manifest = InstallManifest {
+            version: self.version.clone(),
+            requested: requested.clone(),
+            installed_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            source: source.to_string(),
+            extra_packages: extra_packages.to_vec(),
+        };
+
+        let manifest_path = self.install_dir()?.join(constants::INSTALL_MANIFEST_FILE);
+        log::debug!("Writing install manifest to {:?}", manifest_path);
+        fs::write(&manifest_path, toml::to_string_pretty(&manifest)?)?;
+
+        Ok(())
+    }
+
+    /// Read back the manifest written by [`InstalledToolchain::save_manifest`].
+    /// Returns `Ok(None)` when the toolchain was installed with `--no-track`
+    /// (or predates the manifest), in which case callers should fall back to
+    /// the legacy marker-only behavior.
+    pub fn load_manifest(&self) -> Result<Option<InstallManifest>> {
+        let manifest_path = self.install_dir()?.join(constants::INSTALL_MANIFEST_FILE);
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&manifest_path)?;
+        Ok(Some(toml::from_str(&content)?))
+    }
+
     pub fn save_version(&self) -> Result<usize> {
         let version = format!("{}", VersionReq::exact(&self.version));
         save(&version, TOOLCHAIN_FILE)
@@ -74,4 +183,177 @@ where
     let l1 = output.write(content.as_bytes())?;
     let l2 = output.write(b"\n")?;
     Ok(l1 + l2)
-}
\ No newline at end of file
+}
+
+/// Resolve which installed toolchain a shim (or the `run` commands) should
+/// use, following the `py` launcher's precedence:
+///
+///   1. an explicit version/path request (e.g. a CLI argument);
+///   2. an active virtual environment (`$VIRTUAL_ENV`), used directly;
+///   3. the nearest `.python-version` file;
+///   4. the `HYGEIA_PYTHON`/`HYGEIA_PYTHON3` environment variables;
+///   5. the highest installed toolchain.
+///
+/// `toolchains_dir` is the directory under which toolchains are installed,
+/// scanned the same way [`InstalledToolchain::from_path`] already does.
+pub fn resolve_toolchain<P>(
+    explicit: Option<&VersionOrPath>,
+    toolchains_dir: P,
+) -> Result<InstalledToolchain>
+where
+    P: AsRef<Path>,
+{
+    let toolchains_dir = toolchains_dir.as_ref();
+
+    if let Some(version_or_path) = explicit {
+        log::debug!("Resolving explicit request {:?}", version_or_path);
+        return match version_or_path {
+            VersionOrPath::Path(path) => InstalledToolchain::from_path(path)
+                .ok_or_else(|| ToolchainNotInstalled::any().into()),
+            VersionOrPath::VersionReq(version_req) => {
+                InstalledToolchain::from_path_matching(toolchains_dir, version_req)
+                    .ok_or_else(|| ToolchainNotInstalled::new(version_req.clone()).into())
+            }
+        };
+    }
+
+    if let Ok(virtual_env) = env::var("VIRTUAL_ENV") {
+        // Generalizes the poetry special-case already baked into `setup_bash`: when a
+        // virtualenv is active, its own interpreter always wins, bypassing the rest of
+        // the resolution pipeline entirely.
+        let venv_bin = Path::new(&virtual_env).join("bin");
+        log::debug!("Found active virtual environment at {:?}", virtual_env);
+        match InstalledToolchain::from_path(&venv_bin) {
+            Some(toolchain) => return Ok(toolchain),
+            None => log::warn!(
+                "VIRTUAL_ENV={:?} is set but no Python interpreter was found in {:?}; ignoring",
+                virtual_env,
+                venv_bin
+            ),
+        }
+    }
+
+    if let Some(selected) = selected::load_selected_toolchain_file() {
+        let selected = selected?;
+        log::debug!(
+            "Found {} version requirement(s) in {}",
+            selected.versions.len(),
+            TOOLCHAIN_FILE
+        );
+        for version_req in &selected.versions {
+            if let Some(toolchain) =
+                InstalledToolchain::from_path_matching(toolchains_dir, version_req)
+            {
+                return Ok(toolchain);
+            }
+            log::debug!(
+                "No installed toolchain matches {}; trying the next entry in {}",
+                version_req,
+                TOOLCHAIN_FILE
+            );
+        }
+        log::debug!(
+            "No installed toolchain matches any entry in {}; falling through to {} and {}",
+            TOOLCHAIN_FILE,
+            constants::python_env_variable(),
+            constants::python3_env_variable()
+        );
+    }
+
+    for env_variable in &[
+        constants::python_env_variable(),
+        constants::python3_env_variable(),
+    ] {
+        if let Ok(raw) = env::var(env_variable) {
+            let version_req = selected::parse_version_req(&raw)?;
+            log::debug!("Found {} from ${}", version_req, env_variable);
+            if let Some(toolchain) =
+                InstalledToolchain::from_path_matching(toolchains_dir, &version_req)
+            {
+                return Ok(toolchain);
+            }
+            log::debug!(
+                "No installed toolchain matches {} from ${}; trying the next fallback",
+                version_req,
+                env_variable
+            );
+        }
+    }
+
+    log::debug!("No version requested anywhere; falling back to the highest installed toolchain");
+    InstalledToolchain::from_path(toolchains_dir).ok_or_else(|| ToolchainNotInstalled::any().into())
+}
+
+/// Same as [`resolve_toolchain`], but for a shim asked to execute `script`:
+/// when no version was explicitly requested, first try to derive one from the
+/// script's shebang, `py`-launcher style, before falling back to the rest of
+/// the resolution pipeline.
+pub fn resolve_toolchain_for_script<P1, P2>(
+    script: P1,
+    explicit: Option<VersionOrPath>,
+    toolchains_dir: P2,
+) -> Result<InstalledToolchain>
+where
+    P1: AsRef<Path>,
+    P2: AsRef<Path>,
+{
+    let explicit = explicit
+        .or_else(|| version_req_from_shebang(script.as_ref()).map(VersionOrPath::VersionReq));
+
+    resolve_toolchain(explicit.as_ref(), toolchains_dir)
+}
+
+/// Derive a `VersionReq` from a script's shebang line, e.g.
+/// `#!/usr/bin/env python3.10` -> `~3.10`, `#!/usr/bin/python3` -> `>=3`.
+/// Returns `None` when the first line isn't a shebang, doesn't name a Python
+/// interpreter, or doesn't carry a derivable version (e.g. a bare `python`).
+fn version_req_from_shebang<P: AsRef<Path>>(script: P) -> Option<VersionReq> {
+    let file = File::open(script).ok()?;
+    let mut first_line = String::new();
+    BufReader::new(file).read_line(&mut first_line).ok()?;
+
+    let rest = first_line.trim_end().strip_prefix("#!")?.trim();
+    let token = rest.split_whitespace().last()?;
+    let interpreter = Path::new(token).file_name()?.to_str()?;
+
+    version_req_from_interpreter_name(interpreter)
+}
+
+fn version_req_from_interpreter_name(interpreter: &str) -> Option<VersionReq> {
+    match interpreter.strip_prefix("python")? {
+        "" => None,
+        "3" => VersionReq::parse(">=3").ok(),
+        version => VersionReq::parse(&format!("~{}", version)).ok(),
+    }
+}
+
+#[cfg(test)]
+mod shebang_tests {
+    use super::*;
+
+    #[test]
+    fn version_req_from_interpreter_name_bare_python3() {
+        assert_eq!(
+            version_req_from_interpreter_name("python3"),
+            Some(VersionReq::parse(">=3").unwrap())
+        );
+    }
+
+    #[test]
+    fn version_req_from_interpreter_name_minor_version() {
+        assert_eq!(
+            version_req_from_interpreter_name("python3.10"),
+            Some(VersionReq::parse("~3.10").unwrap())
+        );
+    }
+
+    #[test]
+    fn version_req_from_interpreter_name_no_version_falls_through() {
+        assert_eq!(version_req_from_interpreter_name("python"), None);
+    }
+
+    #[test]
+    fn version_req_from_interpreter_name_non_python_falls_through() {
+        assert_eq!(version_req_from_interpreter_name("bash"), None);
+    }
+}